@@ -0,0 +1,40 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use mortar::path::Path;
+
+const PATHS: &[&str] = &[
+    "@package_name//abc:something",
+    "@another_package!/different_dir/../another_dir:another_file",
+    "//a/deeply/nested/directory/structure:target",
+    "!/exact_dir:target",
+    "a_dir:a_file",
+    "!a_dir/../b_dir:escaped\\:target",
+    ":local_target",
+];
+
+fn parse(c: &mut Criterion) {
+    c.bench_function("Path::parse", |b| {
+        b.iter(|| {
+            for path in PATHS {
+                black_box(Path::parse(black_box(*path), "default_package", "cur_dir").unwrap());
+            }
+        })
+    });
+}
+
+fn display(c: &mut Criterion) {
+    let paths = PATHS
+        .iter()
+        .map(|path| Path::new(*path, "default_package", "cur_dir"))
+        .collect::<Vec<_>>();
+
+    c.bench_function("Path::to_string", |b| {
+        b.iter(|| {
+            for path in &paths {
+                black_box(path.to_string());
+            }
+        })
+    });
+}
+
+criterion_group!(benches, parse, display);
+criterion_main!(benches);
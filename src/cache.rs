@@ -0,0 +1,209 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use walkdir::WalkDir;
+
+/// Stores built outputs in `<root>/<key>/` so they can be reused by later builds.
+///
+/// # Examples
+///
+/// ```
+/// use mortar::cache::Cache;
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let cache = Cache::new(dir.path().join("cache"));
+///
+/// assert_eq!(cache.get("abc123"), None);
+/// ```
+pub struct Cache {
+    root: PathBuf,
+}
+
+impl Cache {
+    /// Creates a cache stored in `root`. The directory is created on the first [`Cache::put`].
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// Gets the directory holding the outputs stored under `key`, or [`None`] on a cache miss.
+    pub fn get(&self, key: &str) -> Option<PathBuf> {
+        if !Self::is_valid_key(key) {
+            return None;
+        }
+
+        let entry = self.root.join(key);
+
+        entry.is_dir().then_some(entry)
+    }
+
+    /// Copies the file or directory at `path` into the cache under `key`, replacing any previous
+    /// entry, and returns the directory it was stored in.
+    ///
+    /// The entry is staged in a uniquely named directory next to its final location and renamed
+    /// into place, so [`Cache::get`] never sees a partially written entry, even while other
+    /// threads or processes put the same key.
+    pub fn put(&self, key: &str, path: &Path) -> anyhow::Result<PathBuf> {
+        if !Self::is_valid_key(key) {
+            bail!("Invalid cache key `{}`", key);
+        }
+
+        let name = path
+            .file_name()
+            .with_context(|| format!("Cannot cache `{}`, it has no file name", path.display()))?;
+        let entry = self.root.join(key);
+
+        fs::create_dir_all(&self.root)?;
+
+        // Keys cannot start with `.`, so staging directories never collide with entries.
+        let staging = tempfile::Builder::new()
+            .prefix(".staging-")
+            .tempdir_in(&self.root)?;
+
+        Self::copy(path, &staging.path().join(name))
+            .with_context(|| format!("Failed to cache `{}`", path.display()))?;
+
+        // Existing entries are moved aside before being deleted, so a concurrent put of the same key
+        // never sees a half-deleted entry. Keep trying until this entry is the one in place.
+        loop {
+            match fs::rename(staging.path(), &entry) {
+                Ok(()) => return Ok(entry),
+                Err(error)
+                    if matches!(
+                        error.kind(),
+                        io::ErrorKind::DirectoryNotEmpty | io::ErrorKind::AlreadyExists
+                    ) =>
+                {
+                    let trash = tempfile::Builder::new()
+                        .prefix(".trash-")
+                        .tempdir_in(&self.root)?;
+
+                    match fs::rename(&entry, trash.path().join(key)) {
+                        Err(error) if error.kind() != io::ErrorKind::NotFound => {
+                            return Err(error.into())
+                        }
+                        _ => {}
+                    }
+                }
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+
+    fn is_valid_key(key: &str) -> bool {
+        !key.is_empty() && !key.starts_with('.') && !key.contains(['/', '\\'])
+    }
+
+    fn copy(from: &Path, to: &Path) -> anyhow::Result<()> {
+        if !from.is_dir() {
+            fs::copy(from, to)?;
+            return Ok(());
+        }
+
+        for entry in WalkDir::new(from) {
+            let entry = entry?;
+            let dest = to.join(entry.path().strip_prefix(from)?);
+
+            if entry.file_type().is_dir() {
+                fs::create_dir_all(dest)?;
+            } else {
+                fs::copy(entry.path(), dest)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cache::Cache;
+    use std::fs;
+
+    #[test]
+    fn round_trip_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(dir.path().join("cache"));
+        let output = dir.path().join("out.txt");
+
+        fs::write(&output, "built").unwrap();
+
+        let entry = cache.put("abc123", &output).unwrap();
+
+        assert_eq!(cache.get("abc123"), Some(entry.clone()));
+        assert_eq!(fs::read_to_string(entry.join("out.txt")).unwrap(), "built");
+
+        fs::write(&output, "rebuilt").unwrap();
+        cache.put("abc123", &output).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(entry.join("out.txt")).unwrap(),
+            "rebuilt"
+        );
+    }
+
+    #[test]
+    fn round_trip_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(dir.path().join("cache"));
+        let output = dir.path().join("out");
+
+        fs::create_dir_all(output.join("lib")).unwrap();
+        fs::write(output.join("lib/a.o"), "a").unwrap();
+
+        let entry = cache.put("def456", &output).unwrap();
+
+        assert_eq!(fs::read_to_string(entry.join("out/lib/a.o")).unwrap(), "a");
+    }
+
+    #[test]
+    fn concurrent_put() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(dir.path().join("cache"));
+
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let cache = &cache;
+                let output = dir.path().join(format!("out{}", i));
+
+                scope.spawn(move || {
+                    fs::create_dir_all(&output).unwrap();
+                    fs::write(output.join("out.txt"), i.to_string()).unwrap();
+
+                    for _ in 0..10 {
+                        cache.put("abc123", &output).unwrap();
+                    }
+                });
+            }
+        });
+
+        let entries = fs::read_dir(dir.path().join("cache"))
+            .unwrap()
+            .map(|x| x.unwrap().file_name())
+            .collect::<Vec<_>>();
+
+        assert_eq!(entries, vec!["abc123"]);
+        assert_eq!(
+            fs::read_dir(cache.get("abc123").unwrap()).unwrap().count(),
+            1
+        );
+    }
+
+    #[test]
+    fn miss() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(dir.path().join("cache"));
+        let output = dir.path().join("out.txt");
+
+        assert_eq!(cache.get("abc123"), None);
+
+        fs::write(&output, "built").unwrap();
+        cache.put("abc123", &output).unwrap();
+
+        assert_eq!(cache.get("def456"), None);
+        assert_eq!(cache.get("../cache"), None);
+        assert!(cache.put("../escape", &output).is_err());
+        assert!(cache.put("abc123", &dir.path().join("missing")).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// A [Directed Acyclic Graph](https://en.wikipedia.org/wiki/Directed_acyclic_graph).
 ///
@@ -15,8 +15,17 @@ use std::collections::HashMap;
 ///
 /// assert_eq!(graph.transitive_reduction().iter().map(|x| {let mut y = x.clone(); y.sort(); y}).collect::<Vec<_>>(), vec![vec!["a".to_owned(), "b".to_owned()], vec!["c".to_owned(), "d".to_owned()]]);
 /// ```
+#[derive(Clone, Default)]
 pub struct DAG {
     graph: HashMap<String, Vec<String>>,
+    meta: HashMap<String, NodeMeta>,
+}
+
+/// Scheduling information attached to a node of a [`DAG`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NodeMeta {
+    /// The estimated cost of the node, in arbitrary units.
+    pub cost: u64,
 }
 
 impl DAG {
@@ -24,27 +33,119 @@ impl DAG {
     pub fn new() -> Self {
         DAG {
             graph: HashMap::new(),
+            meta: HashMap::new(),
         }
     }
 
     /// Adds a new node to the DAG.
-    pub fn add_node(&mut self, name: String, deps: Option<Vec<String>>) {
-        self.graph.insert(name, deps.unwrap_or(vec![]));
+    ///
+    /// Returns `false` and leaves the DAG unchanged if the node already exists. See
+    /// [`DAG::upsert_node`] to replace an existing node.
+    pub fn add_node(&mut self, name: String, deps: Option<Vec<String>>) -> bool {
+        if self.graph.contains_key(&name) {
+            return false;
+        }
+
+        self.graph.insert(name, deps.unwrap_or_default());
+
+        true
+    }
+
+    /// Adds a new node to the DAG, replacing the dependencies of the node if it already exists.
+    pub fn upsert_node(&mut self, name: String, deps: Option<Vec<String>>) {
+        self.graph.insert(name, deps.unwrap_or_default());
     }
 
     /// Adds a new dependency to the DAG.
     pub fn add_dep(&mut self, name: String, dep: String) {
         self.graph
             .get_mut(&name)
-            .expect(&format!("Node \"{}\" does not exist", name))
+            .unwrap_or_else(|| panic!("Node \"{}\" does not exist", name))
             .push(dep);
     }
 
+    /// Gets the metadata of a node, or the default metadata if none has been set.
+    pub fn node_meta(&self, name: String) -> NodeMeta {
+        self.meta.get(&name).cloned().unwrap_or_default()
+    }
+
+    /// Sets the metadata of a node.
+    pub fn set_node_meta(&mut self, name: String, meta: NodeMeta) {
+        self.meta.insert(name, meta);
+    }
+
+    /// Merges another DAG into this one.
+    ///
+    /// Nodes present in both graphs keep the union of their dependencies, and metadata from `other`
+    /// takes precedence.
+    pub fn merge(&mut self, other: DAG) {
+        self.meta.extend(other.meta);
+
+        for (name, deps) in other.graph {
+            let existing = self.graph.entry(name).or_default();
+
+            for dep in deps {
+                if !existing.contains(&dep) {
+                    existing.push(dep);
+                }
+            }
+        }
+    }
+
+    /// Checks whether two DAGs have the same nodes and the same dependencies for each node.
+    ///
+    /// The order and duplication of dependencies is ignored, as is node metadata.
+    pub fn structural_eq(&self, other: &DAG) -> bool {
+        self.graph.len() == other.graph.len()
+            && self.graph.iter().all(|(name, deps)| {
+                other.graph.get(name).is_some_and(|other_deps| {
+                    deps.iter().collect::<HashSet<_>>() == other_deps.iter().collect::<HashSet<_>>()
+                })
+            })
+    }
+
+    /// Gets the number of nodes in the DAG.
+    pub fn len(&self) -> usize {
+        self.graph.len()
+    }
+
+    /// Checks whether the DAG has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.graph.is_empty()
+    }
+
+    /// Checks whether a node exists in the DAG.
+    pub fn contains_node(&self, name: String) -> bool {
+        self.graph.contains_key(&name)
+    }
+
+    /// Iterates over the names of the nodes in the DAG, in arbitrary order.
+    pub fn nodes(&self) -> impl Iterator<Item = &String> {
+        self.graph.keys()
+    }
+
+    /// Gets the names of the nodes in the DAG, in sorted order.
+    pub fn nodes_sorted(&self) -> Vec<String> {
+        let mut nodes = self.graph.keys().cloned().collect::<Vec<String>>();
+
+        nodes.sort();
+        nodes
+    }
+
     /// Gets the dependencies of a node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node does not exist. See [`DAG::try_deps`] for a non-panicking version.
     pub fn deps(&self, name: String) -> Vec<String> {
         self.graph[&name].clone()
     }
 
+    /// Gets the dependencies of a node, or [`None`] if the node does not exist.
+    pub fn try_deps(&self, name: String) -> Option<&[String]> {
+        self.graph.get(&name).map(|deps| deps.as_slice())
+    }
+
     /// Gets the nodes that depend on a node.
     pub fn reverse_deps(&self, name: String) -> Vec<String> {
         let mut rdeps: Vec<String> = vec![];
@@ -58,48 +159,148 @@ impl DAG {
         rdeps
     }
 
-    /// Generates the [transitive reduction](https://en.wikipedia.org/wiki/Directed_acyclic_graph#Reachability_relation.2C_transitive_closure.2C_and_transitive_reduction) of the DAG.
-    pub fn transitive_reduction(&self) -> Vec<Vec<String>> {
-        let mut tr = vec![self
+    /// Gets every node that depends on a node, directly or transitively, in sorted order.
+    pub fn transitive_reverse_deps(&self, name: String) -> Vec<String> {
+        let mut rdeps: Vec<String> = vec![];
+        let mut queue = self.reverse_deps(name);
+
+        while let Some(node) = queue.pop() {
+            if !rdeps.contains(&node) {
+                queue.extend(self.reverse_deps(node.clone()));
+                rdeps.push(node);
+            }
+        }
+
+        rdeps.sort();
+        rdeps
+    }
+
+    /// Gets the nodes that nothing depends on, in sorted order.
+    pub fn roots(&self) -> Vec<String> {
+        let mut roots = self
             .graph
             .keys()
-            .map(|x| x.to_owned())
-            .collect::<Vec<String>>()];
-        let mut temp_tr = tr.clone();
+            .filter(|x| self.reverse_deps(x.to_string()).is_empty())
+            .cloned()
+            .collect::<Vec<String>>();
 
-        let mut changed_last = false;
-        let mut changed = false;
+        roots.sort();
+        roots
+    }
 
-        loop {
-            for (id, layer) in tr.iter().enumerate() {
-                for item in layer {
-                    if self.deps(item.to_owned()).iter().any(|x| layer.contains(x)) {
-                        changed = true;
+    /// Gets the nodes that have no dependencies, in sorted order.
+    pub fn leaves(&self) -> Vec<String> {
+        let mut leaves = self
+            .graph
+            .iter()
+            .filter(|(_, deps)| deps.is_empty())
+            .map(|(name, _)| name.to_owned())
+            .collect::<Vec<String>>();
 
-                        if id == temp_tr.len() - 1 {
-                            temp_tr.push(vec![item.to_owned()]);
-                        } else {
-                            temp_tr[id + 1].push(item.to_owned());
-                        }
+        leaves.sort();
+        leaves
+    }
 
-                        let pos = temp_tr[id].iter().position(|x| x == item).unwrap();
+    /// Gets the nodes that are neither depended on nor have dependencies, in sorted order.
+    pub fn orphans(&self) -> Vec<String> {
+        let roots = self.roots();
 
-                        temp_tr[id].remove(pos);
-                    }
-                }
-            }
+        self.leaves()
+            .into_iter()
+            .filter(|x| roots.contains(x))
+            .collect()
+    }
+
+    /// Renders the dependencies of a node as an indented tree, one node per line.
+    ///
+    /// Nodes that have already been printed are marked with `(*)` and not expanded again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node does not exist.
+    pub fn tree(&self, root: String) -> String {
+        if !self.graph.contains_key(&root) {
+            panic!("Node \"{}\" does not exist", root);
+        }
+
+        let mut out = String::new();
+        let mut visited: HashSet<String> = HashSet::new();
+
+        self.write_tree(&root, 0, &mut visited, &mut out);
+        out
+    }
+
+    fn write_tree(
+        &self,
+        name: &str,
+        depth: usize,
+        visited: &mut HashSet<String>,
+        out: &mut String,
+    ) {
+        out.push_str(&"  ".repeat(depth));
+        out.push_str(name);
+
+        if !visited.insert(name.to_owned()) {
+            out.push_str(" (*)\n");
+            return;
+        }
+
+        out.push('\n');
 
-            tr = temp_tr.clone();
+        for dep in self.graph.get(name).into_iter().flatten() {
+            self.write_tree(dep, depth + 1, visited, out);
+        }
+    }
 
-            if !changed_last && changed {
-                break;
+    /// Generates the [transitive reduction](https://en.wikipedia.org/wiki/Directed_acyclic_graph#Reachability_relation.2C_transitive_closure.2C_and_transitive_reduction) of the DAG.
+    ///
+    /// Each node is placed in the first layer after all of its dependencies. Layers are sorted by
+    /// name, so the output only depends on the structure of the DAG.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the graph contains a cycle.
+    pub fn transitive_reduction(&self) -> Vec<Vec<String>> {
+        let mut tr: Vec<Vec<String>> = vec![];
+        let mut placed: HashSet<String> = HashSet::new();
+        let mut remaining = self.nodes_sorted();
+
+        while !remaining.is_empty() {
+            let (layer, rest): (Vec<String>, Vec<String>) =
+                remaining.into_iter().partition(|item| {
+                    self.graph[item]
+                        .iter()
+                        .all(|x| placed.contains(x) || !self.graph.contains_key(x))
+                });
+
+            if layer.is_empty() {
+                panic!("DAG contains a cycle through {:?}", rest);
             }
 
-            changed_last = changed;
+            placed.extend(layer.iter().cloned());
+            tr.push(layer);
+            remaining = rest;
         }
 
         tr
     }
+
+    /// Same as [`DAG::transitive_reduction`] except each layer is ordered by descending cost, so
+    /// the most expensive nodes can be scheduled first. Nodes with equal cost are ordered by name.
+    pub fn execution_levels_sorted_by_cost(&self) -> Vec<Vec<String>> {
+        let mut levels = self.transitive_reduction();
+
+        for level in levels.iter_mut() {
+            level.sort_by(|a, b| {
+                self.node_meta(b.to_owned())
+                    .cost
+                    .cmp(&self.node_meta(a.to_owned()).cost)
+                    .then_with(|| a.cmp(b))
+            });
+        }
+
+        levels
+    }
 }
 
 #[cfg(test)]
@@ -116,4 +317,249 @@ mod tests {
             vec![vec!["a".to_string()], vec!["b".to_string()]]
         );
     }
+
+    #[test]
+    fn transitive_reverse_deps() {
+        let mut graph = crate::dag::DAG::new();
+
+        graph.add_node("a".to_string(), None);
+        graph.add_node("b".to_string(), Some(vec!["a".to_string()]));
+        graph.add_node("c".to_string(), Some(vec!["b".to_string()]));
+        graph.add_node(
+            "d".to_string(),
+            Some(vec!["a".to_string(), "c".to_string()]),
+        );
+        graph.add_node("e".to_string(), None);
+
+        assert_eq!(
+            graph.transitive_reverse_deps("a".to_string()),
+            vec!["b".to_string(), "c".to_string(), "d".to_string()]
+        );
+        assert_eq!(
+            graph.transitive_reverse_deps("c".to_string()),
+            vec!["d".to_string()]
+        );
+        assert!(graph.transitive_reverse_deps("e".to_string()).is_empty());
+    }
+
+    #[test]
+    fn merge() {
+        let mut graph = crate::dag::DAG::new();
+        let mut other = crate::dag::DAG::new();
+
+        graph.add_node("a".to_string(), None);
+        graph.add_node("c".to_string(), Some(vec!["a".to_string()]));
+        other.add_node("b".to_string(), None);
+        other.add_node(
+            "c".to_string(),
+            Some(vec!["a".to_string(), "b".to_string()]),
+        );
+
+        graph.merge(other);
+
+        assert_eq!(graph.deps("a".to_string()), Vec::<String>::new());
+        assert_eq!(graph.deps("b".to_string()), Vec::<String>::new());
+        assert_eq!(
+            graph.deps("c".to_string()),
+            vec!["a".to_string(), "b".to_string()]
+        );
+    }
+
+    #[test]
+    fn roots_leaves_orphans() {
+        let mut graph = crate::dag::DAG::new();
+
+        graph.add_node("a".to_string(), None);
+        graph.add_node("b".to_string(), Some(vec!["a".to_string()]));
+        graph.add_node("c".to_string(), Some(vec!["b".to_string()]));
+        graph.add_node("d".to_string(), None);
+
+        assert_eq!(graph.roots(), vec!["c".to_string(), "d".to_string()]);
+        assert_eq!(graph.leaves(), vec!["a".to_string(), "d".to_string()]);
+        assert_eq!(graph.orphans(), vec!["d".to_string()]);
+    }
+
+    #[test]
+    fn execution_levels_sorted_by_cost() {
+        let mut graph = crate::dag::DAG::new();
+
+        for (name, cost) in [("a", 1), ("b", 5), ("c", 3), ("d", 0)] {
+            graph.add_node(name.to_string(), None);
+            graph.set_node_meta(name.to_string(), crate::dag::NodeMeta { cost });
+        }
+
+        graph.add_node("e".to_string(), Some(vec!["a".to_string()]));
+        graph.add_node("f".to_string(), Some(vec!["b".to_string()]));
+        graph.set_node_meta("f".to_string(), crate::dag::NodeMeta { cost: 2 });
+
+        assert_eq!(
+            graph.execution_levels_sorted_by_cost(),
+            vec![
+                vec![
+                    "b".to_string(),
+                    "c".to_string(),
+                    "a".to_string(),
+                    "d".to_string()
+                ],
+                vec!["f".to_string(), "e".to_string()]
+            ]
+        );
+        assert_eq!(graph.node_meta("e".to_string()).cost, 0);
+    }
+
+    #[test]
+    fn clone() {
+        let mut graph = crate::dag::DAG::default();
+
+        graph.add_node("a".to_string(), None);
+        graph.add_node("b".to_string(), None);
+
+        let mut clone = graph.clone();
+
+        clone.add_dep("b".to_string(), "a".to_string());
+        clone.add_node("c".to_string(), None);
+
+        assert_eq!(graph.deps("b".to_string()), Vec::<String>::new());
+        assert_eq!(graph.leaves(), vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(clone.deps("b".to_string()), vec!["a".to_string()]);
+    }
+
+    #[test]
+    fn introspection() {
+        let mut graph = crate::dag::DAG::new();
+
+        assert!(graph.is_empty());
+
+        graph.add_node("a".to_string(), None);
+        graph.add_node("b".to_string(), Some(vec!["a".to_string()]));
+
+        assert_eq!(graph.len(), 2);
+        assert!(!graph.is_empty());
+        assert!(graph.contains_node("a".to_string()));
+        assert!(!graph.contains_node("c".to_string()));
+        assert_eq!(
+            graph.try_deps("b".to_string()),
+            Some(&["a".to_string()][..])
+        );
+        assert_eq!(graph.try_deps("c".to_string()), None);
+
+        let mut nodes = graph.nodes().collect::<Vec<_>>();
+
+        nodes.sort();
+
+        assert_eq!(nodes, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn add_existing_node() {
+        let mut graph = crate::dag::DAG::new();
+
+        assert!(graph.add_node("a".to_string(), None));
+        assert!(graph.add_node("b".to_string(), Some(vec!["a".to_string()])));
+        assert!(!graph.add_node("b".to_string(), None));
+        assert_eq!(graph.deps("b".to_string()), vec!["a".to_string()]);
+
+        graph.upsert_node("b".to_string(), None);
+
+        assert_eq!(graph.deps("b".to_string()), Vec::<String>::new());
+    }
+
+    #[test]
+    fn deterministic_transitive_reduction() {
+        let build = || {
+            let mut graph = crate::dag::DAG::new();
+
+            for name in ["e", "c", "a", "d", "b", "f"] {
+                graph.add_node(name.to_string(), None);
+            }
+
+            graph.add_dep("b".to_string(), "a".to_string());
+            graph.add_dep("c".to_string(), "b".to_string());
+            graph.add_dep("d".to_string(), "c".to_string());
+            graph.add_dep("f".to_string(), "a".to_string());
+            graph
+        };
+
+        let expected = vec![
+            vec!["a".to_string(), "e".to_string()],
+            vec!["b".to_string(), "f".to_string()],
+            vec!["c".to_string()],
+            vec!["d".to_string()],
+        ];
+
+        assert_eq!(
+            build().nodes_sorted(),
+            vec!["a", "b", "c", "d", "e", "f"]
+                .into_iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+        );
+
+        for _ in 0..100 {
+            assert_eq!(build().transitive_reduction(), expected);
+        }
+    }
+
+    #[test]
+    fn structural_eq() {
+        let build = || {
+            let mut graph = crate::dag::DAG::new();
+
+            graph.add_node("a".to_string(), None);
+            graph.add_node("b".to_string(), None);
+            graph.add_node(
+                "c".to_string(),
+                Some(vec!["a".to_string(), "b".to_string()]),
+            );
+            graph
+        };
+
+        let mut reordered = crate::dag::DAG::new();
+
+        reordered.add_node(
+            "c".to_string(),
+            Some(vec!["b".to_string(), "a".to_string()]),
+        );
+        reordered.add_node("b".to_string(), None);
+        reordered.add_node("a".to_string(), None);
+
+        let mut different = build();
+
+        different.add_dep("b".to_string(), "a".to_string());
+
+        assert!(build().structural_eq(&build()));
+        assert!(build().structural_eq(&reordered));
+        assert!(!build().structural_eq(&different));
+        assert!(!different.structural_eq(&build()));
+    }
+
+    #[test]
+    fn tree() {
+        let mut graph = crate::dag::DAG::new();
+
+        graph.add_node("d".to_string(), None);
+        graph.add_node("b".to_string(), Some(vec!["d".to_string()]));
+        graph.add_node("c".to_string(), Some(vec!["d".to_string()]));
+        graph.add_node(
+            "a".to_string(),
+            Some(vec!["b".to_string(), "c".to_string()]),
+        );
+
+        assert_eq!(
+            graph.tree("a".to_string()),
+            "a\n  b\n    d\n  c\n    d (*)\n"
+        );
+        assert_eq!(graph.tree("d".to_string()), "d\n");
+    }
+
+    #[test]
+    #[should_panic]
+    fn transitive_reduction_cycle() {
+        let mut graph = crate::dag::DAG::new();
+
+        graph.add_node("a".to_string(), Some(vec!["b".to_string()]));
+        graph.add_node("b".to_string(), Some(vec!["a".to_string()]));
+
+        graph.transitive_reduction();
+    }
 }
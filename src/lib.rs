@@ -1,3 +1,5 @@
+pub mod cache;
 pub mod dag;
 pub mod mortar;
 pub mod path;
+pub mod workspace;
@@ -1,8 +1,46 @@
+use std::process::exit;
+
+use clap::{Parser, Subcommand};
 use mortar::mortar::embedded_eval;
+use mortar::path::Path;
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
 
-fn main() {   
+#[derive(Subcommand)]
+enum Command {
+    /// Evaluates a build file.
+    Eval {
+        /// The file to evaluate.
+        file: String,
+    },
+    /// Resolves a label to its fully qualified form.
+    Query {
+        /// The label to resolve, relative to the current directory.
+        label: String,
+    },
+}
+
+fn main() {
     let mortar = mortar::mortar::Mortar::new();
-    let cwd: &str = "src";
 
-    embedded_eval!(mortar, cwd, "star/mortar.star");
+    match Cli::parse().command {
+        Some(Command::Eval { file }) => mortar.eval_file(".", file.as_str()),
+        Some(Command::Query { label }) => match Path::try_from(label.as_str()) {
+            Ok(path) => println!("{}", path),
+            Err(error) => {
+                eprintln!("{}", error.render(&label));
+                exit(1);
+            }
+        },
+        None => {
+            let cwd: &str = "src";
+
+            embedded_eval!(mortar, cwd, "star/mortar.star");
+        }
+    }
 }
@@ -1,68 +1,258 @@
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::process::exit;
+use anyhow::Context;
 use normalize_path::NormalizePath;
 
+use starlark::any::ProvidesStaticType;
 use starlark::environment::{GlobalsBuilder, Module, FrozenModule};
+use starlark::errors::Diagnostic;
 use starlark::eval::Evaluator;
 use starlark::starlark_module;
 use starlark::syntax::{AstModule, Dialect};
-use starlark::values::{Heap, Value};
+use starlark::values::{AllocValue, Heap, Value};
 
+/// Evaluates Mortar build files.
+///
+/// A [`Mortar`] is [`Send`] and [`Sync`], so one instance can be shared between threads to evaluate files concurrently.
 pub struct Mortar {
     globals: starlark::environment::Globals,
+    extension: String,
+}
+
+/// Information about the file being evaluated, passed to native functions through [`Evaluator::extra`].
+#[derive(Debug, ProvidesStaticType)]
+struct EvalContext {
+    /// The directory evaluation started from, which paths in the package are relative to.
+    root: PathBuf,
+    /// The directory containing the file being evaluated.
+    dir: PathBuf,
+    /// The directory containing the file being evaluated, relative to `root` and starting with `/`.
+    package_dir: String,
+}
+
+impl EvalContext {
+    fn new(root: &Path, file: &Path) -> Self {
+        let root = root.normalize();
+        let dir = file.parent().unwrap_or(Path::new(""));
+
+        Self {
+            package_dir: format!("/{}", dir.strip_prefix(&root).unwrap_or(dir).to_string_lossy()),
+            dir: if dir.as_os_str().is_empty() { PathBuf::from(".") } else { dir.to_owned() },
+            root,
+        }
+    }
+
+    /// Creates the context for a file loaded from the file being evaluated.
+    fn for_load(&self, file: &Path) -> Self {
+        Self::new(&self.root, file)
+    }
+
+    /// The root directory, with `.` in place of an empty path.
+    fn root(&self) -> &Path {
+        if self.root.as_os_str().is_empty() { Path::new(".") } else { &self.root }
+    }
+
+    /// Checks whether `file`, which must exist, is inside the root directory once symlinks and `..` are resolved.
+    fn is_in_root(&self, file: &Path) -> anyhow::Result<bool> {
+        Ok(file.canonicalize()?.starts_with(self.root().canonicalize()?))
+    }
+
+    fn get<'a>(eval: &Evaluator<'_, 'a>) -> anyhow::Result<&'a Self> {
+        eval.extra
+            .and_then(|extra| extra.downcast_ref::<Self>())
+            .context("No evaluation context available")
+    }
+}
+
+/// Makes the normalized absolute path `path` relative to `base`, using `..` to leave `base` if needed.
+fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    let common = path.components().zip(base.components()).take_while(|(a, b)| a == b).count();
+
+    base.components().skip(common).map(|_| std::path::Component::ParentDir)
+        .chain(path.components().skip(common))
+        .collect()
 }
 
 #[starlark_module]
 fn globals(builder: &mut GlobalsBuilder) {
-    fn do_something_else<'v>(heap: &'v Heap) -> anyhow::Result<Value> {
+    fn do_something_else<'v>(heap: &'v Heap) -> anyhow::Result<Value<'v>> {
         Ok(heap.alloc_str("potato").to_value())
     }
+
+    /// Reads a file relative to the directory of the file being evaluated. Files outside the root directory are rejected.
+    fn read_file(path: &str, eval: &mut Evaluator) -> anyhow::Result<String> {
+        let context = EvalContext::get(eval)?;
+        let file = context.dir.join(path);
+
+        if !context.is_in_root(&file).with_context(|| format!("Failed to read `{}`", path))? {
+            anyhow::bail!("File `{}` is outside of the root directory `{}`", path, context.root().display());
+        }
+
+        std::fs::read_to_string(&file).with_context(|| format!("Failed to read `{}`", path))
+    }
+
+    /// Finds the files matching any of `patterns`, relative to the directory of the file being evaluated. Files outside the
+    /// root directory are skipped.
+    fn glob(patterns: Vec<&str>, eval: &mut Evaluator) -> anyhow::Result<Vec<String>> {
+        let context = EvalContext::get(eval)?;
+        let dir = &context.dir;
+        let escaped_dir = glob::Pattern::escape(dir.to_str().context("Non-UTF8 package directory")?);
+        let base = std::path::absolute(dir)?.normalize();
+        let mut files = Vec::new();
+
+        for pattern in patterns {
+            for entry in glob::glob(&format!("{}/{}", escaped_dir, pattern))? {
+                let entry = entry?;
+
+                if entry.is_file() && context.is_in_root(&entry)? {
+                    // Normalize first, so `a/../b.txt` and `b.txt` are the same file.
+                    let file = relative_to(&std::path::absolute(&entry)?.normalize(), &base);
+
+                    files.push(file.to_string_lossy().into_owned());
+                }
+            }
+        }
+
+        files.sort();
+        files.dedup();
+
+        Ok(files)
+    }
+
+    /// Resolves a label relative to the package directory of the file being evaluated.
+    fn label(label: &str, eval: &mut Evaluator) -> anyhow::Result<String> {
+        let context = EvalContext::get(eval)?;
+
+        Ok(crate::path::Path::parse(label, "", &context.package_dir)?.to_string())
+    }
+}
+
+impl Default for Mortar {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Mortar {
     pub fn new() -> Self {
+        Self::with_globals(|_| {})
+    }
+
+    /// Creates a new [`Mortar`] whose scripts can also use the globals added by `f`, e.g. native
+    /// functions defined with [`starlark_module`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use starlark::environment::GlobalsBuilder;
+    /// use starlark::starlark_module;
+    ///
+    /// #[starlark_module]
+    /// fn helpers(builder: &mut GlobalsBuilder) {
+    ///     fn project_name() -> anyhow::Result<String> {
+    ///         Ok("mortar".to_owned())
+    ///     }
+    /// }
+    ///
+    /// let mortar = mortar::mortar::Mortar::with_globals(helpers);
+    /// ```
+    pub fn with_globals(f: impl FnOnce(&mut GlobalsBuilder)) -> Self {
         Self {
-            globals: Self::create_globals(),
+            globals: Self::create_globals(f),
+            extension: "star".to_owned(),
         }
     }
 
-    fn create_globals() -> starlark::environment::Globals {
-        GlobalsBuilder::extended().with(globals).build()
+    /// Sets the extension (without a leading `.`) tried when a loaded module does not exist as written. Defaults to `star`.
+    pub fn set_extension<S: AsRef<str>>(&mut self, extension: S) {
+        self.extension = extension.as_ref().to_owned();
     }
 
-    fn get_source(relative_to: &str, file: &str) -> anyhow::Result<String> {
-        Ok(std::fs::read_to_string(std::path::PathBuf::from_iter(vec![relative_to, file]).normalize())?)
+    fn create_globals(f: impl FnOnce(&mut GlobalsBuilder)) -> starlark::environment::Globals {
+        GlobalsBuilder::extended().with(globals).with(f).build()
+    }
+
+    fn get_source(file: &Path) -> anyhow::Result<String> {
+        Ok(std::fs::read_to_string(file)?)
+    }
+
+    /// Finds the file a `load` of `file` refers to, trying in order:
+    ///
+    /// - `file` itself,
+    /// - `file` with the extension appended,
+    /// - `default.<extension>` in the directory `file`,
+    /// - `BUILD.<extension>` in the directory `file`.
+    ///
+    /// Files outside the root directory of the evaluation are rejected, so build files cannot read
+    /// arbitrary files through `load`.
+    fn resolve_module(&self, context: &EvalContext, file: &str) -> anyhow::Result<PathBuf> {
+        let path = context.dir.join(file);
+        let mut with_extension = path.clone().into_os_string();
+
+        with_extension.push(format!(".{}", self.extension));
+
+        let candidates = [
+            path.clone(),
+            PathBuf::from(with_extension),
+            path.join(format!("default.{}", self.extension)),
+            path.join(format!("BUILD.{}", self.extension)),
+        ];
+
+        let found = candidates.iter().find(|x| x.is_file()).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Module `{}` not found, tried: {}",
+                file,
+                candidates.iter().map(|x| format!("`{}`", x.normalize().display())).collect::<Vec<_>>().join(", ")
+            )
+        })?;
+
+        if !context.is_in_root(found)? {
+            anyhow::bail!("Module `{}` is outside of the root directory `{}`", file, context.root().display());
+        }
+
+        Ok(found.normalize())
     }
 
-    fn loader_from_ast(&self, relative_to: &str, ast: &starlark::syntax::AstModule) -> anyhow::Result<HashMap<String, FrozenModule>> {
+    fn loader_from_ast(&self, context: &EvalContext, ast: &starlark::syntax::AstModule) -> anyhow::Result<HashMap<String, FrozenModule>> {
         let mut loads = Vec::new();
         for load in ast.loads() {
-            loads.push((load.module_id.to_owned(), self.get_module(relative_to, load.module_id)?));
+            loads.push((load.module_id.to_owned(), self.get_module(context, load.module_id)?));
         }
         Ok(loads.iter().map(|(a, b)| (a.as_str().to_owned(), b.to_owned())).collect())
     }
 
-    fn borrowed_modules<'a>(modules: &'a HashMap<String, FrozenModule>) -> HashMap<&str, &FrozenModule> {
+    fn borrowed_modules(modules: &HashMap<String, FrozenModule>) -> HashMap<&str, &FrozenModule> {
          modules.iter().map(|(a, b)| (a.as_str(), b)).collect::<HashMap<&str, &FrozenModule>>()
     }
     
-    fn get_module(&self, relative_to: &str, file: &str) -> anyhow::Result<FrozenModule> {
-        let ast = AstModule::parse(file, Self::get_source(relative_to, file)?, &Dialect::Standard)?;
+    fn get_module(&self, parent: &EvalContext, file: &str) -> anyhow::Result<FrozenModule> {
+        let path = self.resolve_module(parent, file)?;
+        let context = parent.for_load(&path);
+        let ast = AstModule::parse(&path.to_string_lossy(), Self::get_source(&path)?, &Dialect::Standard)?;
 
-        let modules = self.loader_from_ast(relative_to, &ast)?;
-        let mut loader = starlark::eval::ReturnFileLoader {modules: &Self::borrowed_modules(&modules) };
+        let modules = self.loader_from_ast(&context, &ast)?;
+        let loader = starlark::eval::ReturnFileLoader {modules: &Self::borrowed_modules(&modules) };
 
         let module = Module::new();
         {
             let mut eval = Evaluator::new(&module);
-            eval.set_loader(&mut loader);
+            eval.set_loader(&loader);
+            eval.extra = Some(&context);
             eval.eval_module(ast, &self.globals)?;
         }
 
-        Ok(module.freeze()?)
+        module.freeze()
     }
     
     fn eval_internal(&self, cwd: &str, filename: &str, content: &str) -> anyhow::Result<i8> {
+        self.eval_internal_with_scope(cwd, filename, content, &HashMap::<String, String>::new())
+    }
+
+    fn eval_internal_with_scope<V>(&self, cwd: &str, filename: &str, content: &str, scope: &HashMap<String, V>) -> anyhow::Result<i8>
+    where
+        V: for<'v> AllocValue<'v> + Clone,
+    {
         let ast: AstModule = AstModule::parse(
             filename,
             content.to_owned(),
@@ -70,40 +260,69 @@ impl Mortar {
         )?;
 
         let normalized_filename = std::path::PathBuf::from_iter(vec![cwd, filename]).normalize();
-        let file_dir = normalized_filename.parent().unwrap().to_str().unwrap();
-        let modules = self.loader_from_ast(file_dir, &ast)?;
-        let mut loader = starlark::eval::ReturnFileLoader {modules: &Self::borrowed_modules(&modules) };
+        let context = EvalContext::new(Path::new(cwd), &normalized_filename);
+        let modules = self.loader_from_ast(&context, &ast)?;
+        let loader = starlark::eval::ReturnFileLoader {modules: &Self::borrowed_modules(&modules) };
         let module: Module = Module::new();
 
         module.set("cwd", module.heap().alloc_str(cwd).to_value());
         module.set("current_file", module.heap().alloc_str(filename).to_value());
 
+        for (name, value) in scope {
+            module.set(name, module.heap().alloc(value.clone()));
+        }
+
         let mut eval: Evaluator = Evaluator::new(&module);
 
-        eval.set_loader(&mut loader);
+        eval.set_loader(&loader);
+        eval.extra = Some(&context);
         eval.eval_module(ast, &self.globals)?;
 
         Ok(0)
     }
     
+    /// Formats an evaluation error as a `filename:line:column: message` line when the error has a
+    /// location so editors can jump to it.
+    pub fn format_error(error: &anyhow::Error) -> String {
+        match error.downcast_ref::<Diagnostic>() {
+            Some(Diagnostic { message, span: Some(span), .. }) => {
+                let location = span.resolve_span();
+
+                format!("{}:{}:{}: {}", span.filename(), location.begin_line + 1, location.begin_column + 1, message)
+            }
+            _ => error.to_string(),
+        }
+    }
+
     pub fn eval<S: AsRef<str>>(&self, cwd: S, filename: S, content: S) {
         self.eval_internal(cwd.as_ref(), filename.as_ref(), content.as_ref()).unwrap_or_else(|error| {
-                println!("{}", error);
+                println!("{}", Self::format_error(&error));
                 exit(1);
             });
     }
 
     pub fn eval_file<S: AsRef<str>>(&self, cwd: S, filename: S) {
-        self.eval_internal(
+        self.eval_file_with_scope(cwd, filename, &HashMap::<String, String>::new());
+    }
+
+    /// Same as [`Mortar::eval_file`] except each entry of `scope` is defined as a variable before the
+    /// file is evaluated, e.g. to pass in build configuration. Values can be anything starlark can
+    /// allocate, such as [`String`], [`i32`] or [`bool`].
+    pub fn eval_file_with_scope<S: AsRef<str>, V>(&self, cwd: S, filename: S, scope: &HashMap<String, V>)
+    where
+        V: for<'v> AllocValue<'v> + Clone,
+    {
+        self.eval_internal_with_scope(
             cwd.as_ref(),
             filename.as_ref(),
-            &std::fs::read_to_string(filename.as_ref()).expect(&format!(
+            &std::fs::read_to_string(filename.as_ref()).unwrap_or_else(|_| panic!(
                 "{}: File `{}` does not exist.",
-                std::env::args().nth(0).unwrap(),
+                std::env::args().next().unwrap(),
                 filename.as_ref()
             )),
+            scope,
         ).unwrap_or_else(|error| {
-                println!("{}", error);
+                println!("{}", Self::format_error(&error));
                 exit(1);
             });
     }
@@ -117,3 +336,356 @@ macro_rules! embedded_eval {
 }
 
 pub use embedded_eval;
+
+#[cfg(test)]
+mod tests {
+    use crate::mortar::Mortar;
+    use starlark::environment::GlobalsBuilder;
+    use starlark::starlark_module;
+    use std::collections::HashMap;
+
+    /// Evaluates `script`, panicking with the evaluation error if it fails.
+    fn eval_ok(mortar: &Mortar, cwd: &str, filename: &str, script: &str) {
+        mortar.eval_internal(cwd, filename, script).unwrap();
+    }
+
+    #[test]
+    fn read_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let cwd = dir.path().to_str().unwrap();
+
+        std::fs::write(dir.path().join("sources.txt"), "a.c\nb.c\n").unwrap();
+
+        eval_ok(
+            &Mortar::new(),
+            cwd,
+            "BUILD.star",
+            r#"
+def check():
+    if read_file("sources.txt") != "a.c\nb.c\n":
+        fail("wrong contents")
+
+check()
+"#,
+        );
+        assert!(Mortar::new()
+            .eval_internal(cwd, "BUILD.star", r#"read_file("missing.txt")"#)
+            .is_err());
+    }
+
+    #[test]
+    fn read_file_outside_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("workspace");
+        let cwd = root.to_str().unwrap();
+
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "outside").unwrap();
+        std::fs::write(root.join("secret.txt"), "inside").unwrap();
+
+        for path in ["../../secret.txt", "../../workspace/../secret.txt"] {
+            let error = Mortar::new()
+                .eval_internal(cwd, "sub/BUILD.star", &format!("read_file(\"{}\")", path))
+                .unwrap_err()
+                .to_string();
+
+            assert!(error.contains("outside of the root directory"), "{}", error);
+        }
+
+        eval_ok(
+            &Mortar::new(),
+            cwd,
+            "sub/BUILD.star",
+            r#"
+def check():
+    if read_file("../secret.txt") != "inside":
+        fail("wrong contents")
+
+check()
+"#,
+        );
+    }
+
+    #[test]
+    fn glob() {
+        let dir = tempfile::tempdir().unwrap();
+        let cwd = dir.path().to_str().unwrap();
+
+        for file in ["main.rs", "src/lib.rs", "src/nested/mod.rs", "README.md"] {
+            let path = dir.path().join(file);
+
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+
+        eval_ok(
+            &Mortar::new(),
+            cwd,
+            "BUILD.star",
+            r#"
+def check():
+    if glob(["**/*.rs", "src/*.rs"]) != ["main.rs", "src/lib.rs", "src/nested/mod.rs"]:
+        fail("wrong files")
+    if glob(["*.md"]) != ["README.md"]:
+        fail("wrong files")
+
+check()
+"#,
+        );
+    }
+
+    #[test]
+    fn glob_outside_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("workspace");
+        let cwd = root.to_str().unwrap();
+
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "").unwrap();
+        std::fs::write(root.join("inside.txt"), "").unwrap();
+
+        eval_ok(
+            &Mortar::new(),
+            cwd,
+            "sub/BUILD.star",
+            r#"
+def check():
+    if glob(["../../*.txt"]) != []:
+        fail("matched outside the root: " + str(glob(["../../*.txt"])))
+    if glob(["../*.txt", "../../workspace/*.txt"]) != ["../inside.txt"]:
+        fail("wrong files: " + str(glob(["../*.txt", "../../workspace/*.txt"])))
+
+check()
+"#,
+        );
+    }
+
+    #[test]
+    fn concurrent_eval() {
+        let mortar = Mortar::new();
+
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let mortar = &mortar;
+
+                scope.spawn(move || {
+                    mortar
+                        .eval_internal(".", "BUILD.star", &format!("x = {} * 2", i))
+                        .unwrap();
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn label() {
+        let dir = tempfile::tempdir().unwrap();
+        let cwd = dir.path().to_str().unwrap();
+
+        std::fs::create_dir_all(dir.path().join("foo/rules")).unwrap();
+        std::fs::write(
+            dir.path().join("foo/rules/defs.star"),
+            "rules_label = label(\":defs\")\n",
+        )
+        .unwrap();
+
+        eval_ok(
+            &Mortar::new(),
+            cwd,
+            "foo/BUILD.star",
+            r#"
+load("rules/defs.star", "rules_label")
+
+def check():
+    if label(":bar") != "//foo:bar":
+        fail("wrong label: " + label(":bar"))
+    if label("baz:qux") != "//foo/baz:qux":
+        fail("wrong label: " + label("baz:qux"))
+    if label("@other//abs:olute") != "@other//abs:olute":
+        fail("wrong label: " + label("@other//abs:olute"))
+    if label("//:x") != "//:x":
+        fail("wrong label: " + label("//:x"))
+    if rules_label != "//foo/rules:defs":
+        fail("wrong label: " + rules_label)
+
+check()
+"#,
+        );
+    }
+
+    #[test]
+    fn error_in_loaded_module() {
+        let dir = tempfile::tempdir().unwrap();
+        let cwd = dir.path().to_str().unwrap();
+
+        std::fs::create_dir_all(dir.path().join("rules")).unwrap();
+        std::fs::write(
+            dir.path().join("rules/defs.star"),
+            "x = 1\ny = undefined_variable\n",
+        )
+        .unwrap();
+
+        let error = Mortar::new()
+            .eval_internal(cwd, "BUILD.star", r#"load("rules/defs.star", "x")"#)
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains(&format!("{}/rules/defs.star:2:5", cwd)), "{}", error);
+        assert!(error.contains("y = undefined_variable"), "{}", error);
+    }
+
+    #[test]
+    fn resolve_module() {
+        let dir = tempfile::tempdir().unwrap();
+        let cwd = dir.path().to_str().unwrap();
+
+        for (file, name) in [
+            ("exact.star", "exact"),
+            ("rules/cc.star", "cc"),
+            ("lib/default.star", "lib"),
+            ("pkg/BUILD.star", "pkg"),
+            ("custom.mortar", "custom"),
+        ] {
+            let path = dir.path().join(file);
+
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, format!("name = \"{}\"\n", name)).unwrap();
+        }
+
+        let mut mortar = Mortar::new();
+
+        for (load, name) in [
+            ("exact.star", "exact"),
+            ("rules/cc", "cc"),
+            ("lib", "lib"),
+            ("pkg", "pkg"),
+        ] {
+            let script = format!(
+                "load(\"{}\", \"name\")\n\ndef check():\n    if name != \"{}\":\n        fail(name)\n\ncheck()\n",
+                load, name
+            );
+
+            mortar.eval_internal(cwd, "BUILD.star", &script).unwrap();
+        }
+
+        let error = mortar
+            .eval_internal(cwd, "BUILD.star", r#"load("missing", "name")"#)
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("Module `missing` not found"), "{}", error);
+        assert!(mortar
+            .eval_internal(cwd, "BUILD.star", r#"load("custom", "name")"#)
+            .is_err());
+
+        mortar.set_extension("mortar");
+
+        assert!(mortar
+            .eval_internal(cwd, "BUILD.star", r#"load("custom", "name")"#)
+            .is_ok());
+    }
+
+    #[test]
+    fn load_outside_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("workspace");
+        let cwd = root.to_str().unwrap();
+
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(dir.path().join("outside.star"), "x = 1\n").unwrap();
+        std::fs::write(root.join("inside.star"), "x = 1\n").unwrap();
+
+        for load in [
+            "../../outside".to_owned(),
+            "../../workspace/../outside.star".to_owned(),
+            dir.path().join("outside.star").to_str().unwrap().to_owned(),
+        ] {
+            let error = Mortar::new()
+                .eval_internal(cwd, "sub/BUILD.star", &format!("load(\"{}\", \"x\")", load))
+                .unwrap_err()
+                .to_string();
+
+            assert!(error.contains("outside of the root directory"), "{}", error);
+        }
+
+        eval_ok(&Mortar::new(), cwd, "sub/BUILD.star", r#"load("../inside", "x")"#);
+    }
+
+    #[test]
+    fn eval_with_scope() {
+        let dir = tempfile::tempdir().unwrap();
+        let cwd = dir.path().to_str().unwrap();
+        let file = dir.path().join("BUILD.star");
+        let script = r#"
+def check():
+    if platform != "linux":
+        fail("wrong platform: " + platform)
+
+check()
+"#;
+
+        std::fs::write(&file, script).unwrap();
+        std::fs::write(
+            dir.path().join("jobs.star"),
+            "def check():\n    if jobs * 2 != 8:\n        fail(\"wrong jobs\")\n\ncheck()\n",
+        )
+        .unwrap();
+
+        Mortar::new().eval_file_with_scope(
+            cwd,
+            file.to_str().unwrap(),
+            &HashMap::from([("platform".to_owned(), "linux".to_owned())]),
+        );
+        Mortar::new().eval_file_with_scope(
+            cwd,
+            dir.path().join("jobs.star").to_str().unwrap(),
+            &HashMap::from([("jobs".to_owned(), 4)]),
+        );
+        assert!(Mortar::new()
+            .eval_internal(cwd, "BUILD.star", script)
+            .is_err());
+    }
+
+    #[starlark_module]
+    fn custom_globals(builder: &mut GlobalsBuilder) {
+        fn double(x: i32) -> anyhow::Result<i32> {
+            Ok(x * 2)
+        }
+    }
+
+    #[test]
+    fn with_globals() {
+        let script = r#"
+def check():
+    if double(21) != 42:
+        fail("wrong result")
+
+check()
+"#;
+
+        eval_ok(
+            &Mortar::with_globals(custom_globals),
+            ".",
+            "BUILD.star",
+            script,
+        );
+        assert!(Mortar::new()
+            .eval_internal(".", "BUILD.star", script)
+            .is_err());
+    }
+
+    #[test]
+    fn format_error() {
+        let error = Mortar::new()
+            .eval_internal(".", "BUILD.star", "x = 1\ny = x + undefined_variable\n")
+            .unwrap_err();
+
+        assert_eq!(Mortar::format_error(&error), "BUILD.star:2:9: Variable `undefined_variable` not found");
+
+        let error = Mortar::new()
+            .eval_internal(".", "BUILD.star", r#"load("missing", "x")"#)
+            .unwrap_err();
+
+        assert_eq!(Mortar::format_error(&error), error.to_string());
+    }
+}
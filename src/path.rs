@@ -1,7 +1,25 @@
 use normalize_path::NormalizePath;
+use std::fmt;
 use std::path::PathBuf;
 
-#[derive(Debug, PartialEq, Eq)]
+/// A reference to a target, written `@package//dir:target`.
+///
+/// The `@package` prefix is optional and `//` may be replaced with `!/` to make the path exact.
+/// Without either separator the directory is relative to the current one. The current directory is
+/// itself relative to the package root, so parsed directories always start with `/` and the same
+/// directory compares equal however it was written. Absolute paths may omit
+/// `:target`, in which case the target is the last component of the directory, so `//a/b` is
+/// short for `//a/b:b`.
+///
+/// - Package names may contain ASCII letters, digits, `_`, `-`, `.`, `+` and `~`. Other names
+///   can be quoted, as in `@"weird repo"//dir:target`, with `"` and `\` escaped by a backslash.
+/// - Directories may contain anything except `:`.
+/// - Targets may contain anything except `:`, which can be escaped with `\`. A `/` in a target
+///   names a file in a subdirectory, as in `//pkg:subdir/file.txt`, so targets may not have
+///   empty, `.` or `..` segments.
+///
+/// Paths are ordered by package, then directory, then target, then exactness.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Path {
     pub package: String,
     pub dir: String,
@@ -24,7 +42,7 @@ impl Path {
     ///
     /// assert_eq!(
     ///     Path::new("!something:abc", "test", "a_dir"),
-    ///     Path {package: "test".to_owned(), dir: "a_dir/something".to_owned(), target: "abc".to_owned(), exact: true}
+    ///     Path {package: "test".to_owned(), dir: "/a_dir/something".to_owned(), target: "abc".to_owned(), exact: true}
     /// )
     /// ```
     pub fn new<S: AsRef<str>>(path: S, cur_package: S, cur_dir: S) -> Self {
@@ -32,70 +50,378 @@ impl Path {
     }
 
     /// Same as [`Path::new`] except it returns a [`Result`].
-    pub fn parse<S: AsRef<str>>(path: S, cur_package: S, cur_dir: S) -> Result<Self, &'static str> {
-        if let Some(caps) = regex::Regex::new("^((?:@[a-zA-Z_-]+)?)([!/])(/[^:]+):([^:/]+)$")
-            .unwrap()
-            .captures(path.as_ref())
-        {
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mortar::path::Path;
+    ///
+    /// let error = Path::parse("@//:", "package", ".").unwrap_err();
+    ///
+    /// assert_eq!(error.message, "Expected a target");
+    /// assert_eq!(error.offset, 4);
+    /// ```
+    pub fn parse<S: AsRef<str>>(path: S, cur_package: S, cur_dir: S) -> Result<Self, ParseError> {
+        let path = path.as_ref();
+        let Some((location, target)) = split_target(path) else {
+            return Self::parse_inferred(path, cur_package.as_ref());
+        };
+        let target =
+            unescape_target(target).map_err(|error| error.offset_by(location.len() + 1))?;
+
+        if let Some((package, separator, dir)) = split_absolute(location)? {
             Ok(Self {
-                package: {
-                    if caps[1].starts_with("@") {
-                        caps[1][1..].to_owned()
-                    } else {
-                        cur_package.as_ref().to_owned()
-                    }
-                },
-                dir: {
-                    if caps[3].len() > 0 {
-                        PathBuf::from(caps[3].to_owned())
-                            .normalize()
-                            .to_str()
-                            .unwrap()
-                            .to_owned()
-                    } else {
-                        "/".to_owned()
-                    }
-                },
-                target: { caps[4].to_owned() },
-                exact: &caps[2] == "!",
+                package: package.unwrap_or_else(|| cur_package.as_ref().to_owned()),
+                dir: PathBuf::from(dir).normalize().to_str().unwrap().to_owned(),
+                target,
+                exact: separator == '!',
             })
-        } else if let Some(caps) = regex::Regex::new("^(!?)((?:(?:.?/)?[^:]+)?):([^:/]+)$")
-            .unwrap()
-            .captures(path.as_ref())
-        {
-            println!("oi");
-
-            let mut pb = PathBuf::new();
-
-            println!("{}", &caps[2]);
-
-            cur_dir
-                .as_ref()
-                .to_owned()
-                .split("/")
-                .map(|x| pb.push(x))
-                .for_each(drop);
-            caps[2]
-                .to_owned()
-                .split("/")
-                .map(|x| pb.push(x))
-                .for_each(drop);
+        } else {
+            let (exact, dir) = match location.strip_prefix('!') {
+                Some(dir) => (true, dir),
+                None => (false, location),
+            };
+
+            let mut pb = PathBuf::from("/");
+
+            cur_dir.as_ref().split('/').for_each(|x| pb.push(x));
+            dir.split('/').for_each(|x| pb.push(x));
 
             Ok(Self {
                 package: cur_package.as_ref().to_owned(),
                 dir: pb.normalize().to_str().unwrap().to_owned(),
-                target: caps[3].to_owned(),
-                exact: &caps[1] == "!",
+                target,
+                exact,
             })
+        }
+    }
+
+    /// Parses an absolute path without a target, which names the last component of its directory.
+    fn parse_inferred(path: &str, cur_package: &str) -> Result<Self, ParseError> {
+        let error = ParseError::new("Expected `:` followed by a target", path.len());
+        let (package, separator, dir) = split_absolute(path)?.ok_or(error.clone())?;
+        let dir = PathBuf::from(dir).normalize().to_str().unwrap().to_owned();
+        let target = dir.rsplit('/').next().unwrap_or_default().to_owned();
+
+        if target.is_empty() {
+            return Err(error);
+        }
+
+        Ok(Self {
+            package: package.unwrap_or_else(|| cur_package.to_owned()),
+            dir,
+            target,
+            exact: separator == '!',
+        })
+    }
+
+    /// Same as [`Path::parse`] except it also rejects targets that cannot safely name a file.
+    ///
+    /// Once escapes are removed, targets may not contain control characters or leading or trailing
+    /// whitespace, and the targets of exact paths may not contain path separators.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mortar::path::Path;
+    ///
+    /// assert!(Path::parse_strict("a_dir:a_file", "package", ".").is_ok());
+    /// assert!(Path::parse_strict("a_dir:a\tfile", "package", ".").is_err());
+    /// assert!(Path::parse_strict("a_dir:a_file ", "package", ".").is_err());
+    /// ```
+    pub fn parse_strict<S: AsRef<str>>(
+        path: S,
+        cur_package: S,
+        cur_dir: S,
+    ) -> Result<Self, ParseError> {
+        let raw = path.as_ref();
+        let path = Self::parse(raw, cur_package.as_ref(), cur_dir.as_ref())?;
+        let target = path.target.as_str();
+
+        let error = if let Some(offset) = target.find(char::is_control) {
+            ParseError::new("Target contains a control character", offset)
+        } else if target.starts_with(char::is_whitespace) {
+            ParseError::new("Target has leading whitespace", 0)
+        } else if target.ends_with(char::is_whitespace) {
+            ParseError::new("Target has trailing whitespace", target.trim_end().len())
+        } else if let Some(offset) = target.find(['/', '\\']).filter(|_| path.exact) {
+            ParseError::new("Target of an exact path contains a path separator", offset)
         } else {
-            Err("WTF MAN, THAT'S INVALID.")
+            return Ok(path);
+        };
+
+        // Offsets are into the unescaped target. Inferred targets have no text of their own to
+        // point into, so their errors point at the end of the input.
+        Err(match split_target(raw) {
+            Some((location, escaped)) => ParseError::new(
+                error.message,
+                location.len() + 1 + escaped_offset(escaped, error.offset),
+            ),
+            None => ParseError::new(error.message, raw.len()),
+        })
+    }
+}
+
+impl TryFrom<&str> for Path {
+    type Error = ParseError;
+
+    /// Parses a path with no current package, relative to `.`.
+    ///
+    /// Use [`Path::parse`] to supply a different package and directory.
+    fn try_from(path: &str) -> Result<Self, Self::Error> {
+        Self::parse(path, "", ".")
+    }
+}
+
+/// An error produced when a [`Path`] cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// What went wrong.
+    pub message: &'static str,
+    /// The byte offset into the input at which parsing failed.
+    pub offset: usize,
+}
+
+impl ParseError {
+    fn new(message: &'static str, offset: usize) -> Self {
+        Self { message, offset }
+    }
+
+    fn offset_by(self, offset: usize) -> Self {
+        Self::new(self.message, self.offset + offset)
+    }
+
+    /// Renders the error under the input that produced it, with a caret pointing at the offset.
+    ///
+    /// ```
+    /// use mortar::path::Path;
+    ///
+    /// let error = Path::try_from("//a:b:c").unwrap_err();
+    ///
+    /// assert_eq!(error.render("//a:b:c"), "//a:b:c\n     ^ Unescaped `:` in target");
+    /// ```
+    pub fn render(&self, input: &str) -> String {
+        let column = input
+            .get(..self.offset)
+            .map_or(self.offset, |x| x.chars().count());
+
+        format!("{}\n{}^ {}", input, " ".repeat(column), self.message)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn is_package_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+' | '~')
+}
+
+/// The package, separator and directory of an absolute location.
+type Absolute<'a> = (Option<String>, char, &'a str);
+
+/// Splits a path at the `:` before its target, skipping over a quoted package
+/// name.
+fn split_target(path: &str) -> Option<(&str, &str)> {
+    let start = path.strip_prefix("@\"").map_or(0, |rest| {
+        2 + unquote(rest).map_or(rest.len(), |(_, len)| len)
+    });
+    let colon = start + path[start..].find(':')?;
+
+    Some((&path[..colon], &path[colon + 1..]))
+}
+
+/// Reads a quoted package name, starting after the opening quote, returning
+/// the unescaped name and the length of the quoted text including the closing
+/// quote, or [`None`] if the quote is never closed.
+fn unquote(quoted: &str) -> Option<(String, usize)> {
+    let mut name = String::new();
+    let mut chars = quoted.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((name, i + 1)),
+            '\\' => name.push(chars.next()?.1),
+            _ => name.push(c),
         }
     }
+
+    None
+}
+
+/// Splits an absolute location (`@package//dir`, `@"package"//dir` or `!/dir`)
+/// into its package, separator and directory, or returns [`None`] if it is
+/// relative.
+///
+/// Locations starting with `@` are always absolute, so a malformed one is an
+/// error rather than a relative directory.
+fn split_absolute(location: &str) -> Result<Option<Absolute<'_>>, ParseError> {
+    let (package, rest) = match location.strip_prefix('@') {
+        Some(rest) if rest.starts_with('"') => {
+            let (package, len) = unquote(&rest[1..]).ok_or(ParseError::new(
+                "Unterminated quoted package name",
+                location.len(),
+            ))?;
+
+            if package.is_empty() {
+                return Err(ParseError::new("Expected a package name", 2));
+            }
+
+            (Some(package), &rest[1 + len..])
+        }
+        Some(rest) => {
+            let end = rest.find(|c| !is_package_char(c)).unwrap_or(rest.len());
+
+            if end == 0 {
+                return Err(ParseError::new("Expected a package name", 1));
+            }
+
+            (Some(rest[..end].to_owned()), &rest[end..])
+        }
+        None => (None, location),
+    };
+
+    let separator = match rest.get(..2) {
+        Some("//") => '/',
+        Some("!/") => '!',
+        _ if package.is_some() => {
+            return Err(ParseError::new(
+                "Expected `//` or `!/` after the package name",
+                location.len() - rest.len(),
+            ))
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some((package, separator, &rest[1..])))
+}
+
+impl fmt::Display for Path {
+    /// Formats the path in its fully qualified form.
+    ///
+    /// Relative directories are rendered relative to the package root, package
+    /// names that need it are quoted, and `:` and `\` in the target are escaped
+    /// with a backslash so that the output parses back to the same [`Path`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mortar::path::Path;
+    ///
+    /// let path = Path::new("@package_name!/abc:something", "abc", ".");
+    ///
+    /// assert_eq!(path.to_string(), "@package_name!/abc:something");
+    /// assert_eq!(Path::new(path.to_string(), "abc".to_owned(), ".".to_owned()), path);
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.package.chars().any(|c| !is_package_char(c)) {
+            write!(f, "@\"")?;
+
+            for c in self.package.chars() {
+                if matches!(c, '"' | '\\') {
+                    write!(f, "\\")?;
+                }
+
+                write!(f, "{}", c)?;
+            }
+
+            write!(f, "\"")?;
+        } else if !self.package.is_empty() {
+            write!(f, "@{}", self.package)?;
+        }
+
+        write!(f, "{}", if self.exact { "!" } else { "/" })?;
+
+        if !self.dir.starts_with('/') {
+            write!(f, "/")?;
+        }
+
+        write!(f, "{}:", self.dir)?;
+
+        for c in self.target.chars() {
+            if matches!(c, ':' | '\\') {
+                write!(f, "\\")?;
+            }
+
+            write!(f, "{}", c)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Removes the backslash escapes added by [`Path`]'s [`Display`](fmt::Display)
+/// implementation, failing if the target is empty, contains an unescaped `:`,
+/// or has an empty, `.` or `..` segment that could name a file outside its
+/// directory.
+fn unescape_target(target: &str) -> Result<String, ParseError> {
+    let mut out = String::with_capacity(target.len());
+    let mut chars = target.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            ':' => return Err(ParseError::new("Unescaped `:` in target", i)),
+            '\\' => match chars.next() {
+                Some((_, c)) if c != '\n' => out.push(c),
+                _ => return Err(ParseError::new("Invalid escape in target", i)),
+            },
+            _ => out.push(c),
+        }
+    }
+
+    if out.is_empty() {
+        return Err(ParseError::new("Expected a target", 0));
+    }
+
+    let mut start = 0;
+
+    for segment in out.split('/') {
+        let message = match segment {
+            "" => "Empty segment in target",
+            "." | ".." => "Target segments may not be `.` or `..`",
+            _ => {
+                start += segment.len() + 1;
+                continue;
+            }
+        };
+
+        return Err(ParseError::new(message, escaped_offset(target, start)));
+    }
+
+    Ok(out)
+}
+
+/// Maps a byte offset into an unescaped target back to the offset of the same
+/// character in the escaped `target`.
+fn escaped_offset(target: &str, offset: usize) -> usize {
+    let mut unescaped = 0;
+    let mut chars = target.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        if unescaped >= offset {
+            return i;
+        }
+
+        let c = if c == '\\' {
+            chars.next().map_or(c, |(_, c)| c)
+        } else {
+            c
+        };
+
+        unescaped += c.len_utf8();
+    }
+
+    target.len()
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::path::Path;
+    use crate::path::{ParseError, Path};
+    use std::collections::HashSet;
 
     #[test]
     fn relative_path() {
@@ -103,7 +429,7 @@ mod tests {
             Path::new("a_dir:a_file", "default_package", "cur_dir"),
             Path {
                 package: "default_package".to_owned(),
-                dir: "cur_dir/a_dir".to_owned(),
+                dir: "/cur_dir/a_dir".to_owned(),
                 target: "a_file".to_owned(),
                 exact: false
             }
@@ -126,4 +452,457 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn trailing_slash_is_insignificant() {
+        assert_eq!(
+            Path::new("//a/:b", "default_package", "cur_dir"),
+            Path::new("//a:b", "default_package", "cur_dir")
+        );
+        assert_eq!(
+            Path::new("a_dir/:a_file", "default_package", "cur_dir"),
+            Path::new("a_dir:a_file", "default_package", "cur_dir")
+        );
+        assert_eq!(Path::new("a:b", "p", "."), Path::new("//a:b", "p", "."));
+    }
+
+    #[test]
+    fn root_dir() {
+        assert_eq!(
+            Path::new("//:x", "default_package", "cur_dir"),
+            Path {
+                package: "default_package".to_owned(),
+                dir: "/".to_owned(),
+                target: "x".to_owned(),
+                exact: false
+            }
+        );
+        assert_eq!(
+            Path::new("@r!/:x", "default_package", "cur_dir"),
+            Path {
+                package: "r".to_owned(),
+                dir: "/".to_owned(),
+                target: "x".to_owned(),
+                exact: true
+            }
+        );
+        assert_eq!(
+            Path::new("//a/../..:b", "default_package", "cur_dir").dir,
+            "/"
+        );
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for target in ["a:b", "x[0]", "with space", "back\\slash", "a/b"] {
+            for dir in ["/some/dir", "/"] {
+                for exact in [false, true] {
+                    let path = Path {
+                        package: "package".to_owned(),
+                        dir: dir.to_owned(),
+                        target: target.to_owned(),
+                        exact,
+                    };
+
+                    assert_eq!(
+                        Path::new(path.to_string(), "other".to_owned(), ".".to_owned()),
+                        path
+                    );
+                }
+            }
+        }
+
+        for path in ["a_dir:a_file", "!a_dir/../b_dir:a_file", ":t"] {
+            let path = Path::new(path, "package", "cur_dir");
+
+            assert_eq!(
+                Path::new(path.to_string(), "other".to_owned(), "other_dir".to_owned()),
+                path
+            );
+        }
+
+        for path in ["a:b", ":t", "@package//a:b"] {
+            let path = Path::try_from(path).unwrap();
+
+            assert_eq!(Path::try_from(path.to_string().as_str()), Ok(path));
+        }
+    }
+
+    #[test]
+    fn subdirectory_target() {
+        let path = Path::new("//a:b/c.txt", "default_package", "cur_dir");
+
+        assert_eq!(path.target, "b/c.txt");
+        assert_eq!(path.to_string(), "@default_package//a:b/c.txt");
+        assert_eq!(
+            Path::new(path.to_string(), "other".to_owned(), ".".to_owned()),
+            path
+        );
+        assert_eq!(
+            Path::new("d:e/f.txt", "default_package", "cur_dir"),
+            Path {
+                package: "default_package".to_owned(),
+                dir: "/cur_dir/d".to_owned(),
+                target: "e/f.txt".to_owned(),
+                exact: false
+            }
+        );
+        assert_eq!(
+            Path::new("//a:b\\/c.txt", "default_package", "cur_dir"),
+            path
+        );
+    }
+
+    #[test]
+    fn display_relative_dir() {
+        let path = Path::new("!a_dir:a_file", "default_package", "cur_dir");
+
+        assert_eq!(path.to_string(), "@default_package!/cur_dir/a_dir:a_file");
+        assert_eq!(
+            Path::new(path.to_string(), "other".to_owned(), ".".to_owned()),
+            path
+        );
+        assert_eq!(Path::new(":t", "p", ".").to_string(), "@p//:t");
+    }
+
+    #[test]
+    fn invalid_path() {
+        for path in [
+            "no_target",
+            "a_dir:",
+            "a_dir:a:b",
+            "a_dir:trailing\\",
+            "@foo bar//a:b",
+            "@a@b//c:d",
+            "@//:x",
+            "//a:../../etc/passwd",
+            "//a:./b",
+            "a:b/",
+        ] {
+            assert!(Path::parse(path, "default_package", "cur_dir").is_err());
+        }
+    }
+
+    #[test]
+    fn ordering() {
+        let mut paths = [
+            Path::new("@b//a:a", "default_package", "."),
+            Path::new("@a//b:a", "default_package", "."),
+            Path::new("@a!/a:b", "default_package", "."),
+            Path::new("@a//a:b", "default_package", "."),
+            Path::new("@a//a:a", "default_package", "."),
+        ];
+
+        paths.sort();
+
+        assert_eq!(
+            paths.iter().map(|x| x.to_string()).collect::<Vec<_>>(),
+            vec!["@a//a:a", "@a//a:b", "@a!/a:b", "@a//b:a", "@b//a:a"]
+        );
+    }
+
+    #[test]
+    fn hash() {
+        let paths = HashSet::from([
+            Path::new("//a:b", "default_package", "cur_dir"),
+            Path::new("//a/:b", "default_package", "cur_dir"),
+            Path::new("@default_package//a/../a:b", "other_package", "cur_dir"),
+            Path::new("!/a:b", "default_package", "cur_dir"),
+            Path::new("a:b", "default_package", "."),
+        ]);
+
+        assert_eq!(paths.len(), 2);
+    }
+
+    #[test]
+    fn strict_target() {
+        assert!(Path::parse_strict("a_dir:a_file", "default_package", "cur_dir").is_ok());
+        assert!(Path::parse_strict("a_dir:a\\/file", "default_package", "cur_dir").is_ok());
+        assert_eq!(
+            Path::parse_strict("a_dir:a\tfile", "default_package", "cur_dir"),
+            Err(ParseError {
+                message: "Target contains a control character",
+                offset: 7
+            })
+        );
+        assert_eq!(
+            Path::parse_strict("a_dir:a_file ", "default_package", "cur_dir"),
+            Err(ParseError {
+                message: "Target has trailing whitespace",
+                offset: 12
+            })
+        );
+        assert_eq!(
+            Path::parse_strict("a_dir: a_file", "default_package", "cur_dir"),
+            Err(ParseError {
+                message: "Target has leading whitespace",
+                offset: 6
+            })
+        );
+        assert_eq!(
+            Path::parse_strict("!a_dir:a\\/file", "default_package", "cur_dir"),
+            Err(ParseError {
+                message: "Target of an exact path contains a path separator",
+                offset: 8
+            })
+        );
+        assert_eq!(
+            Path::parse_strict("a_dir:\\ a_file", "default_package", "cur_dir"),
+            Err(ParseError {
+                message: "Target has leading whitespace",
+                offset: 6
+            })
+        );
+        assert_eq!(
+            Path::parse_strict("a_dir:a\\:b\\ ", "default_package", "cur_dir"),
+            Err(ParseError {
+                message: "Target has trailing whitespace",
+                offset: 10
+            })
+        );
+        assert_eq!(
+            Path::parse_strict("a_dir:a\\:\tb", "default_package", "cur_dir"),
+            Err(ParseError {
+                message: "Target contains a control character",
+                offset: 9
+            })
+        );
+        assert_eq!(
+            Path::parse_strict("!a_dir:sub/file", "default_package", "cur_dir"),
+            Err(ParseError {
+                message: "Target of an exact path contains a path separator",
+                offset: 10
+            })
+        );
+    }
+
+    #[test]
+    fn strict_inferred_target() {
+        assert_eq!(
+            Path::parse_strict("@r!/foo/bar", "default_package", "cur_dir"),
+            Ok(Path::new("@r!/foo/bar:bar", "default_package", "cur_dir"))
+        );
+        assert_eq!(
+            Path::parse_strict("//foo/bar ", "default_package", "cur_dir"),
+            Err(ParseError {
+                message: "Target has trailing whitespace",
+                offset: 10
+            })
+        );
+    }
+
+    #[test]
+    fn error_offset() {
+        for (path, message, offset) in [
+            ("@@foo", "Expected a package name", 1),
+            ("@//:x", "Expected a package name", 1),
+            (
+                "@foo bar//a:b",
+                "Expected `//` or `!/` after the package name",
+                4,
+            ),
+            (
+                "@a@b//c:d",
+                "Expected `//` or `!/` after the package name",
+                2,
+            ),
+            ("@r/:x", "Expected `//` or `!/` after the package name", 2),
+            ("@r", "Expected `//` or `!/` after the package name", 2),
+            (
+                "//a:../../etc/passwd",
+                "Target segments may not be `.` or `..`",
+                4,
+            ),
+            ("//a:b/../../c", "Target segments may not be `.` or `..`", 6),
+            ("//a:./b", "Target segments may not be `.` or `..`", 4),
+            ("a:b/", "Empty segment in target", 4),
+            ("a:b//c", "Empty segment in target", 4),
+            ("a:/b", "Empty segment in target", 2),
+            ("a:b\\/\\/c", "Empty segment in target", 5),
+            ("@\"\"//a:b", "Expected a package name", 2),
+            (
+                "@\"a\"b//c:d",
+                "Expected `//` or `!/` after the package name",
+                4,
+            ),
+            ("@\"abc//a:b", "Unterminated quoted package name", 10),
+            ("@\"abc\\\"//a:b", "Unterminated quoted package name", 12),
+            ("@//:", "Expected a target", 4),
+            ("//a:b:c", "Unescaped `:` in target", 5),
+            ("a_dir:trailing\\", "Invalid escape in target", 14),
+        ] {
+            assert_eq!(
+                Path::parse(path, "default_package", "cur_dir"),
+                Err(ParseError { message, offset })
+            );
+        }
+    }
+
+    #[test]
+    fn render_error() {
+        for (path, rendered) in [
+            ("@@foo", "@@foo\n ^ Expected a package name"),
+            ("a_dir:a:b", "a_dir:a:b\n       ^ Unescaped `:` in target"),
+            ("é_dir:a:b", "é_dir:a:b\n       ^ Unescaped `:` in target"),
+        ] {
+            assert_eq!(
+                Path::parse(path, "default_package", "cur_dir")
+                    .unwrap_err()
+                    .render(path),
+                rendered
+            );
+        }
+    }
+
+    #[test]
+    fn character_classes() {
+        assert_eq!(
+            Path::new("@a.b//c-d:e.f", "default_package", "cur_dir"),
+            Path {
+                package: "a.b".to_owned(),
+                dir: "/c-d".to_owned(),
+                target: "e.f".to_owned(),
+                exact: false
+            }
+        );
+        assert_eq!(
+            Path::new("@a-b_2//c.d/e-f:g-h.i", "default_package", "cur_dir"),
+            Path {
+                package: "a-b_2".to_owned(),
+                dir: "/c.d/e-f".to_owned(),
+                target: "g-h.i".to_owned(),
+                exact: false
+            }
+        );
+        assert_eq!(
+            Path::new("c.d/e f:g h", "default_package", "cur_dir"),
+            Path {
+                package: "default_package".to_owned(),
+                dir: "/cur_dir/c.d/e f".to_owned(),
+                target: "g h".to_owned(),
+                exact: false
+            }
+        );
+    }
+
+    #[test]
+    fn inferred_target() {
+        assert_eq!(
+            Path::parse("//foo", "default_package", "cur_dir"),
+            Ok(Path::new("//foo:foo", "default_package", "cur_dir"))
+        );
+        assert_eq!(
+            Path::parse("@r!/foo/bar", "default_package", "cur_dir"),
+            Ok(Path::new("@r!/foo/bar:bar", "default_package", "cur_dir"))
+        );
+        assert_eq!(
+            Path::parse("//foo/bar/", "default_package", "cur_dir"),
+            Ok(Path::new("//foo/bar:bar", "default_package", "cur_dir"))
+        );
+
+        for (path, message, offset) in [
+            ("//:", "Expected a target", 3),
+            ("@r//:", "Expected a target", 5),
+            ("//", "Expected `:` followed by a target", 2),
+            ("//foo/..", "Expected `:` followed by a target", 8),
+            ("foo", "Expected `:` followed by a target", 3),
+        ] {
+            assert_eq!(
+                Path::parse(path, "default_package", "cur_dir"),
+                Err(ParseError { message, offset })
+            );
+        }
+    }
+
+    #[test]
+    fn package_names() {
+        assert_eq!(
+            Path::new("@weird+repo~1//a:b", "default_package", "cur_dir"),
+            Path {
+                package: "weird+repo~1".to_owned(),
+                dir: "/a".to_owned(),
+                target: "b".to_owned(),
+                exact: false
+            }
+        );
+
+        let quoted = Path::new("@\"weird repo\"!/a:b", "default_package", "cur_dir");
+
+        assert_eq!(
+            quoted,
+            Path {
+                package: "weird repo".to_owned(),
+                dir: "/a".to_owned(),
+                target: "b".to_owned(),
+                exact: true
+            }
+        );
+        assert_eq!(quoted.to_string(), "@\"weird repo\"!/a:b");
+        assert_eq!(
+            Path::new(quoted.to_string(), "a".to_owned(), ".".to_owned()),
+            quoted
+        );
+        assert_eq!(
+            Path::new("@\"plain\"//a:b", "default_package", "cur_dir").to_string(),
+            "@plain//a:b"
+        );
+
+        let escaped = Path {
+            package: "a\"b:c\\d".to_owned(),
+            dir: "/e".to_owned(),
+            target: "f".to_owned(),
+            exact: false,
+        };
+
+        assert_eq!(escaped.to_string(), "@\"a\\\"b:c\\\\d\"//e:f");
+        assert_eq!(
+            Path::new(escaped.to_string(), "a".to_owned(), ".".to_owned()),
+            escaped
+        );
+        assert_eq!(
+            Path::new("@\"a:b\"//c", "default_package", "cur_dir"),
+            Path {
+                package: "a:b".to_owned(),
+                dir: "/c".to_owned(),
+                target: "c".to_owned(),
+                exact: false
+            }
+        );
+    }
+
+    #[test]
+    fn exact_separator() {
+        let exact = Path::new("@r!/pkg:t", "default_package", "cur_dir");
+        let recursive = Path::new("@r//pkg:t", "default_package", "cur_dir");
+
+        assert!(exact.exact);
+        assert!(!recursive.exact);
+        assert_ne!(exact, recursive);
+        assert_eq!(
+            Path::new(exact.to_string(), "a".to_owned(), ".".to_owned()),
+            exact
+        );
+        assert_eq!(
+            Path::new(recursive.to_string(), "a".to_owned(), ".".to_owned()),
+            recursive
+        );
+    }
+
+    #[test]
+    fn try_from_str() {
+        assert_eq!(
+            Path::try_from("@package//a_dir:a_file"),
+            Ok(Path::new("@package//a_dir:a_file", "", "."))
+        );
+        assert_eq!(
+            Path::try_from("a_dir:a_file"),
+            Ok(Path {
+                package: "".to_owned(),
+                dir: "/a_dir".to_owned(),
+                target: "a_file".to_owned(),
+                exact: false
+            })
+        );
+        assert!(Path::try_from("a_dir").is_err());
+    }
 }
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::path::Path;
+
+/// Maps packages to the directories they live in.
+///
+/// # Examples
+///
+/// ```
+/// use mortar::path::Path;
+/// use mortar::workspace::Workspace;
+///
+/// let mut workspace = Workspace::new("main".to_owned(), "/src/main".into());
+///
+/// workspace.add_package("dep".to_owned(), "/src/dep".into());
+///
+/// assert_eq!(
+///     workspace.resolve(&Path::new("@dep//lib:lib.c", "main", ".")),
+///     Some("/src/dep/lib/lib.c".into())
+/// );
+/// ```
+pub struct Workspace {
+    packages: HashMap<String, PathBuf>,
+}
+
+impl Workspace {
+    /// Creates a new workspace whose main package `name` is rooted at `root`.
+    ///
+    /// Paths without a package also resolve relative to `root`.
+    pub fn new(name: String, root: PathBuf) -> Self {
+        let mut packages = HashMap::new();
+
+        packages.insert(String::new(), root.clone());
+        packages.insert(name, root);
+
+        Self { packages }
+    }
+
+    /// Adds or replaces the root directory of a package.
+    pub fn add_package(&mut self, name: String, root: PathBuf) {
+        self.packages.insert(name, root);
+    }
+
+    /// Gets the root directory of a package.
+    pub fn package_root(&self, name: &str) -> Option<&PathBuf> {
+        self.packages.get(name)
+    }
+
+    /// Resolves a path to `<package root>/<dir>/<target>`, or [`None`] if its package is unknown.
+    pub fn resolve(&self, path: &Path) -> Option<PathBuf> {
+        let mut resolved = self.package_root(&path.package)?.clone();
+
+        resolved.push(path.dir.trim_start_matches('/'));
+        resolved.push(&path.target);
+
+        Some(resolved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::path::Path;
+    use crate::workspace::Workspace;
+    use std::path::PathBuf;
+
+    fn workspace() -> Workspace {
+        let mut workspace = Workspace::new("main".to_owned(), PathBuf::from("/src/main"));
+
+        workspace.add_package("external".to_owned(), PathBuf::from("/deps/external"));
+        workspace
+    }
+
+    #[test]
+    fn resolve_same_package() {
+        assert_eq!(
+            workspace().resolve(&Path::new("//a/b:c.txt", "main", ".")),
+            Some(PathBuf::from("/src/main/a/b/c.txt"))
+        );
+        assert_eq!(
+            workspace().resolve(&Path::new("d:e.txt", "main", "a")),
+            Some(PathBuf::from("/src/main/a/d/e.txt"))
+        );
+        assert_eq!(
+            workspace().resolve(&Path::try_from("a:b").unwrap()),
+            Some(PathBuf::from("/src/main/a/b"))
+        );
+    }
+
+    #[test]
+    fn resolve_subdirectory_target() {
+        assert_eq!(
+            workspace().resolve(&Path::new("//a:b/c.txt", "main", ".")),
+            Some(PathBuf::from("/src/main/a/b/c.txt"))
+        );
+    }
+
+    #[test]
+    fn resolve_external_package() {
+        assert_eq!(
+            workspace().resolve(&Path::new("@external!/lib:lib.c", "main", ".")),
+            Some(PathBuf::from("/deps/external/lib/lib.c"))
+        );
+        assert_eq!(
+            workspace().resolve(&Path::new("@unknown//lib:lib.c", "main", ".")),
+            None
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use std::process::Command;
+
+fn mortar(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_mortar"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn eval() {
+    let output = mortar(&["eval", "tests/scripts/hello.star"]);
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Hello from greeting.star"));
+}
+
+#[test]
+fn eval_read_file_outside_root() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("workspace");
+
+    std::fs::create_dir_all(root.join("sub")).unwrap();
+    std::fs::write(dir.path().join("secret.txt"), "outside").unwrap();
+    std::fs::write(root.join("secret.txt"), "inside").unwrap();
+    std::fs::write(
+        root.join("sub/BUILD.star"),
+        "print(read_file(\"../../secret.txt\"))\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mortar"))
+        .args(["eval", "sub/BUILD.star"])
+        .current_dir(&root)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(!output.status.success());
+    assert!(
+        stdout.contains("is outside of the root directory `.`"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn eval_missing_file() {
+    assert!(!mortar(&["eval", "tests/scripts/missing.star"])
+        .status
+        .success());
+}
+
+#[test]
+fn query() {
+    let output = mortar(&["query", "@package!/a/../b:c"]);
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "@package!/b:c\n");
+}
+
+#[test]
+fn query_invalid() {
+    let output = mortar(&["query", "not_a_label"]);
+
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "not_a_label\n           ^ Expected `:` followed by a target\n"
+    );
+}